@@ -1,11 +1,101 @@
 use crate::{get_file_len, FileHandle, FileHandle::*, LengthSpec};
-use std::{
-    fs::File,
-    io::{self, Read, Seek},
-};
+use std::{borrow::Borrow, fs::File, io, sync::Arc};
 
 // private interface
 
+/// Positioned read, modeled on `pread`/`pwrite`-style APIs: reads bytes
+/// starting at an absolute file offset, independent of the file cursor,
+/// so several readers can safely share the same `fd`. On Unix the cursor is
+/// left untouched; on Windows `seek_read` leaves it at the end of the read,
+/// but no read here depends on where it points.
+///
+/// This is `pub` only because it shows up in the trait bounds of
+/// [`crate::ContinuableFile`]; it is implemented for [`File`], `&File` and
+/// `Arc<File>` and is not meant to be implemented for other types.
+pub trait PosRead {
+    fn read_at(&self, buf: &mut [u8], offset: u64) -> io::Result<usize>;
+
+    fn read_exact_at(&self, mut buf: &mut [u8], mut offset: u64) -> io::Result<()> {
+        while !buf.is_empty() {
+            match self.read_at(buf, offset) {
+                Ok(0) => break,
+                Ok(n) => {
+                    buf = &mut buf[n..];
+                    offset += n as u64;
+                }
+                Err(ref e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+            }
+        }
+        if !buf.is_empty() {
+            Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "failed to fill whole buffer",
+            ))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+#[cfg(unix)]
+impl PosRead for File {
+    #[inline]
+    fn read_at(&self, buf: &mut [u8], offset: u64) -> io::Result<usize> {
+        std::os::unix::fs::FileExt::read_at(self, buf, offset)
+    }
+
+    #[inline]
+    fn read_exact_at(&self, buf: &mut [u8], offset: u64) -> io::Result<()> {
+        std::os::unix::fs::FileExt::read_exact_at(self, buf, offset)
+    }
+}
+
+#[cfg(windows)]
+impl PosRead for File {
+    fn read_at(&self, mut buf: &mut [u8], mut offset: u64) -> io::Result<usize> {
+        use std::os::windows::fs::FileExt;
+        let mut total = 0;
+        while !buf.is_empty() {
+            match self.seek_read(buf, offset) {
+                Ok(0) => break,
+                Ok(n) => {
+                    total += n;
+                    buf = &mut buf[n..];
+                    offset += n as u64;
+                }
+                Err(ref e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(total)
+    }
+}
+
+impl PosRead for &File {
+    #[inline]
+    fn read_at(&self, buf: &mut [u8], offset: u64) -> io::Result<usize> {
+        (**self).read_at(buf, offset)
+    }
+
+    #[inline]
+    fn read_exact_at(&self, buf: &mut [u8], offset: u64) -> io::Result<()> {
+        (**self).read_exact_at(buf, offset)
+    }
+}
+
+impl PosRead for Arc<File> {
+    #[inline]
+    fn read_at(&self, buf: &mut [u8], offset: u64) -> io::Result<usize> {
+        (**self).read_at(buf, offset)
+    }
+
+    #[inline]
+    fn read_exact_at(&self, buf: &mut [u8], offset: u64) -> io::Result<()> {
+        (**self).read_exact_at(buf, offset)
+    }
+}
+
 fn open_as_mmap(fh: &File, offset: u64, len: usize) -> io::Result<memmap2::Mmap> {
     unsafe {
         memmap2::MmapOptions::new()
@@ -15,39 +105,69 @@ fn open_as_mmap(fh: &File, offset: u64, len: usize) -> io::Result<memmap2::Mmap>
     }
 }
 
+/// `cow` ? (copy-on-write: private dirty pages, never written back to `fh`)
+/// : (shared read-write: dirty pages are written back to `fh` on flush/unmap)
+fn open_as_mmap_mut(fh: &File, offset: u64, len: usize, cow: bool) -> io::Result<memmap2::MmapMut> {
+    unsafe {
+        let mut opts = memmap2::MmapOptions::new();
+        opts.offset(offset).len(len);
+        if cow {
+            opts.map_copy(fh)
+        } else {
+            opts.map_mut(fh)
+        }
+    }
+}
+
+/// Evaluates how many bytes a read of `lenspec` starting at `offset` should
+/// cover, capping at the remaining file length (obtained from `flen_hint`,
+/// falling back to `get_file_len`). Returns `None` (read until EOF) when
+/// neither bound is known or the result is not below `isize::MAX` (the
+/// largest length a mapping can represent). Shared by [`read_part_from_file_intern`] and
+/// [`read_part_for_write_intern`], which only differ in how they get `fh`
+/// and what they do with the result.
+fn eval_len(fh: &File, offset: u64, lenspec: LengthSpec, flen_hint: Option<u64>) -> io::Result<Option<usize>> {
+    let maxlen_i = std::isize::MAX as usize;
+
+    if lenspec.is_exact && lenspec.bound.map(|len| len > maxlen_i) == Some(true) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "length is too big",
+        ));
+    }
+
+    Ok([
+        lenspec.bound,
+        // `saturating_sub`: `offset` past the file end (e.g. an
+        // offset/size pair dereferenced from untrusted archive contents,
+        // as `CachedFile` is meant to support) must cap out at 0 bytes
+        // remaining rather than underflow
+        flen_hint
+            .or_else(|| get_file_len(fh))
+            .map(|lx| lx.saturating_sub(offset) as usize),
+    ]
+    .iter()
+    .flatten()
+    .min()
+    .and_then(|&mxl| if mxl < maxlen_i { Some(mxl) } else { None }))
+}
+
 /// Reads a part of the file contents,
 /// use this if the file is too big and needs to be read in parts,
 /// starting at [`offset`] and until the given LengthSpec is met.
 ///
+/// The buffered fallback uses positioned reads (`pread`/`ReadAt`), so this
+/// never depends on a shared file cursor and `fh` only needs to be `&T`.
+///
 /// @param flen_hint : used to cache the call to [`get_file_len`]
-pub(crate) fn read_part_from_file_intern(
-    fh: &mut File,
+pub(crate) fn read_part_from_file_intern<T: PosRead + Borrow<File>>(
+    fh: &T,
     offset: u64,
     lenspec: LengthSpec,
     flen_hint: Option<u64>,
 ) -> io::Result<FileHandle> {
     // evaluate file length
-    let evl: Option<usize> = {
-        let maxlen_i = std::isize::MAX as usize;
-
-        if lenspec.is_exact && lenspec.bound.map(|len| len > maxlen_i) == Some(true) {
-            return Err(io::Error::new(
-                io::ErrorKind::InvalidInput,
-                "length is too big",
-            ));
-        }
-
-        [
-            lenspec.bound,
-            flen_hint
-                .or_else(|| get_file_len(fh))
-                .map(|lx| (lx - offset) as usize),
-        ]
-        .iter()
-        .flatten()
-        .min()
-        .and_then(|&mxl| if mxl < maxlen_i { Some(mxl) } else { None })
-    };
+    let evl = eval_len(fh.borrow(), offset, lenspec, flen_hint)?;
 
     // check common cases
     match evl {
@@ -56,7 +176,11 @@ pub(crate) fn read_part_from_file_intern(
         }
         Some(lx) => {
             // do NOT try to map the file if the size is unknown
-            if let Ok(ret) = open_as_mmap(fh, offset, lx) {
+            if let Ok(ret) = open_as_mmap(fh.borrow(), offset, lx) {
+                if let Some(advice) = lenspec.advice {
+                    // performance hint only; ignore failures (e.g. unsupported platform)
+                    let _ = ret.advise(advice.into());
+                }
                 return Ok(Mapped(ret));
             }
         }
@@ -64,30 +188,81 @@ pub(crate) fn read_part_from_file_intern(
     }
 
     // use Buffered as fallback
-    fh.seek(io::SeekFrom::Start(offset))?;
+    read_buffered_at(fh, offset, evl, lenspec.is_exact).map(Buffered)
+}
+
+/// reads `evl` bytes (or, if `evl` is `None`, until EOF) starting at `offset`,
+/// via positioned reads, for use as a [`FileHandle::Buffered`] fallback
+fn read_buffered_at<T: PosRead>(
+    fh: &T,
+    offset: u64,
+    evl: Option<usize>,
+    is_exact: bool,
+) -> io::Result<Box<[u8]>> {
     let contents = match evl {
         Some(0) => Vec::new(),
         Some(lx) => {
             let mut contents = core::iter::repeat(0u8).take(lx).collect::<Vec<_>>();
-            if lenspec.is_exact {
-                fh.read_exact(&mut contents)?;
+            if is_exact {
+                fh.read_exact_at(&mut contents, offset)?;
             } else {
-                let bcnt = fh.read(&mut contents)?;
+                let bcnt = fh.read_at(&mut contents, offset)?;
                 contents.truncate(bcnt);
             }
             contents
         }
         None => {
             let mut contents = Vec::new();
-            if let Err(x) = fh.read_to_end(&mut contents) {
-                if lenspec.is_exact || contents.is_empty() {
-                    return Err(x);
+            let mut cur = offset;
+            let mut chunk = [0u8; 8192];
+            loop {
+                match fh.read_at(&mut chunk, cur) {
+                    Ok(0) => break,
+                    Ok(n) => {
+                        contents.extend_from_slice(&chunk[..n]);
+                        cur += n as u64;
+                    }
+                    Err(x) => {
+                        if is_exact || contents.is_empty() {
+                            return Err(x);
+                        }
+                        break;
+                    }
                 }
             }
             contents
         }
     };
-    Ok(Buffered(contents.into_boxed_slice()))
+    Ok(contents.into_boxed_slice())
+}
+
+/// Maps a part of the file contents for writing (see [`crate::read_part_for_write`]).
+/// Falls back to a [`FileHandle::Buffered`] in-memory copy if the mapping fails;
+/// such a buffer is edited in memory only and [`FileHandle::flush`] is a no-op for it.
+pub(crate) fn read_part_for_write_intern(
+    fh: &mut File,
+    offset: u64,
+    lenspec: LengthSpec,
+    cow: bool,
+) -> io::Result<FileHandle> {
+    let evl = eval_len(fh, offset, lenspec, None)?;
+
+    match evl {
+        Some(0) => return Ok(Buffered(Vec::new().into())),
+        Some(lx) => {
+            if let Ok(ret) = open_as_mmap_mut(fh, offset, lx, cow) {
+                return Ok(MappedMut(ret));
+            }
+        }
+        None => {}
+    }
+
+    read_buffered_at(fh, offset, evl, lenspec.is_exact).map(Buffered)
+}
+
+#[inline]
+pub(crate) fn seek_out_of_range_err() -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, "seek out of range")
 }
 
 #[inline]
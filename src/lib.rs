@@ -2,8 +2,13 @@ mod backend;
 
 use crate::backend::*;
 use delegate_attr::delegate;
-use memmap2::Mmap;
-use std::{fs, io, ops::Deref};
+use memmap2::{Mmap, MmapMut};
+use std::{
+    borrow::Borrow,
+    fs,
+    io::{self, BufRead},
+    ops::Deref,
+};
 
 // public interface
 
@@ -11,6 +16,8 @@ use std::{fs, io, ops::Deref};
 #[must_use]
 pub enum FileHandle {
     Mapped(Mmap),
+    /// writable mapping, see [`read_part_for_write`]
+    MappedMut(MmapMut),
     Buffered(Box<[u8]>),
 }
 
@@ -23,9 +30,45 @@ impl FileHandle {
     pub fn as_slice(&self) -> &[u8] {
         match self {
             Mapped(ref dt) => dt,
+            MappedMut(ref dt) => dt,
             Buffered(ref dt) => dt,
         }
     }
+
+    /// Returns a mutable slice pointing to the contents of the
+    /// [`FileHandle`], if it is writable, i.e. [`FileHandle::MappedMut`]
+    /// or [`FileHandle::Buffered`]. Returns `None` for a read-only
+    /// [`FileHandle::Mapped`].
+    #[inline]
+    pub fn as_mut_slice(&mut self) -> Option<&mut [u8]> {
+        match self {
+            Mapped(_) => None,
+            MappedMut(ref mut dt) => Some(dt),
+            Buffered(ref mut dt) => Some(dt),
+        }
+    }
+
+    /// Gives the kernel a hint about how this handle's contents will be
+    /// accessed (via `madvise`/`posix_madvise`, see [`memmap2::Mmap::advise`]).
+    /// A no-op that always succeeds on a [`FileHandle::Buffered`] handle.
+    pub fn advise(&self, adv: Advice) -> io::Result<()> {
+        match self {
+            Mapped(ref dt) => dt.advise(adv.into()),
+            MappedMut(ref dt) => dt.advise(adv.into()),
+            Buffered(_) => Ok(()),
+        }
+    }
+
+    /// Flushes outstanding writes of a [`FileHandle::MappedMut`] to the
+    /// underlying file, see [`memmap2::MmapMut::flush`]. A no-op for
+    /// [`FileHandle::Mapped`] and [`FileHandle::Buffered`], since neither
+    /// one writes back to the file on its own.
+    pub fn flush(&self) -> io::Result<()> {
+        match self {
+            MappedMut(ref dt) => dt.flush(),
+            Mapped(_) | Buffered(_) => Ok(()),
+        }
+    }
 }
 
 impl AsRef<[u8]> for FileHandle {
@@ -44,12 +87,55 @@ impl Deref for FileHandle {
     }
 }
 
+/// Access-pattern advice for a mapped [`FileHandle`], passed through to
+/// [`memmap2::Mmap::advise`] (which wraps `posix_madvise`/`madvise`).
+/// Purely a performance hint: [`FileHandle::advise`] ignores the case where
+/// the handle is [`FileHandle::Buffered`], and callers may ignore errors too.
+///
+/// `MADV_DONTNEED` is deliberately not exposed here: memmap2 only offers it
+/// through its `unsafe` `unchecked_advise`, since it can zero/repopulate
+/// pages out from under an outstanding `&[u8]` borrow of the handle.
+#[derive(Clone, Copy, Eq, PartialEq, Hash, Debug)]
+pub enum Advice {
+    /// No special treatment, the default.
+    Normal,
+    /// Expect sequential access from the current position onward.
+    Sequential,
+    /// Expect access in a random order.
+    Random,
+    /// Expect access in the near future; the kernel may start readahead.
+    WillNeed,
+}
+
+impl std::default::Default for Advice {
+    #[inline(always)]
+    fn default() -> Self {
+        Advice::Normal
+    }
+}
+
+impl From<Advice> for memmap2::Advice {
+    fn from(adv: Advice) -> Self {
+        match adv {
+            Advice::Normal => memmap2::Advice::Normal,
+            Advice::Sequential => memmap2::Advice::Sequential,
+            Advice::Random => memmap2::Advice::Random,
+            Advice::WillNeed => memmap2::Advice::WillNeed,
+        }
+    }
+}
+
 #[derive(Clone, Copy, Eq, PartialEq, Hash, Debug)]
 pub struct LengthSpec {
     /// `bound` ? (read at most $n bytes) : (read until EOF)
     pub bound: Option<usize>,
     /// `is_exact` ? (request exactly length or fail) : (request biggest readable slice with length as upper bound)
     pub is_exact: bool,
+    /// `Some(_)` ? (apply this access-pattern advice to a successfully
+    /// mapped handle) : (apply no hint at all, i.e. skip the `madvise` call).
+    /// Ignored (and never fails) for a buffered fallback read.
+    /// See [`Advice`] and [`FileHandle::advise`].
+    pub advice: Option<Advice>,
 }
 
 impl std::default::Default for LengthSpec {
@@ -59,6 +145,7 @@ impl std::default::Default for LengthSpec {
         Self {
             bound: None,
             is_exact: false,
+            advice: None,
         }
     }
 }
@@ -74,11 +161,12 @@ pub fn get_file_len(fh: &fs::File) -> Option<u64> {
 /// Reads the file contents
 pub fn read_from_file(fh: io::Result<fs::File>) -> io::Result<FileHandle> {
     read_part_from_file(
-        &mut fh?,
+        &fh?,
         0,
         LengthSpec {
             bound: None,
             is_exact: true,
+            ..Default::default()
         },
     )
 }
@@ -87,31 +175,89 @@ pub fn read_from_file(fh: io::Result<fs::File>) -> io::Result<FileHandle> {
 /// use this if the file is too big and needs to be read in parts,
 /// starting at offset and until the given LengthSpec is met.
 /// if you want a more ergonomic interface, use [`ContinuableFile`] or [`ChunkedFile`].
-/// fh is a reference because this function is intended to be called multiple times
+/// fh is a shared reference because this function is intended to be called multiple
+/// times and only ever does positioned reads, so it never depends on a shared file cursor.
 #[inline]
 pub fn read_part_from_file(
-    fh: &mut fs::File,
+    fh: &fs::File,
     offset: u64,
     len: LengthSpec,
 ) -> io::Result<FileHandle> {
     read_part_from_file_intern(fh, offset, len, None)
 }
 
+/// Maps a part of the file contents for writing, starting at offset and
+/// covering the length given by `len`. An unbounded `len` (`bound: None`)
+/// is *not* guaranteed to land in memory: it resolves to the file's actual
+/// remaining length (via [`get_file_len`]) exactly like a bounded `len`
+/// does, so it maps [`FileHandle::MappedMut`] just the same whenever that
+/// length can be determined; [`FileHandle::Buffered`] is only returned for
+/// an empty range (0 bytes remaining) or as a fallback for when establishing
+/// the mapping itself fails (including when the file length can't be
+/// determined at all).
+///
+/// `cow` selects the mapping mode: `false` maps the range read-write and
+/// shared, so writes are visible to other mappers and are written back to
+/// `fh`; `true` maps it copy-on-write and private, so writes only dirty the
+/// caller's own pages and are never written back. Either way, if the mapping
+/// itself cannot be established, this falls back to an in-memory
+/// [`FileHandle::Buffered`] copy, which is likewise never written back;
+/// call [`FileHandle::flush`] to persist a shared (`cow == false`)
+/// [`FileHandle::MappedMut`].
+pub fn read_part_for_write(
+    fh: &mut fs::File,
+    offset: u64,
+    len: LengthSpec,
+    cow: bool,
+) -> io::Result<FileHandle> {
+    read_part_for_write_intern(fh, offset, len, cow)
+}
+
+/// A [`fs::File`]-backed cursor that reads successive parts without ever
+/// seeking, so it can be built over a plain `File`, a `&File`, or an
+/// `Arc<File>` shared with other [`ContinuableFile`]/[`ChunkedFile`] views
+/// of disjoint regions of the same underlying file.
 #[must_use]
-pub struct ContinuableFile {
-    file: fs::File,
+pub struct ContinuableFile<F = fs::File> {
+    file: F,
     flen: Option<u64>,
     offset: u64,
 }
 
+/// Drives [`ChunkedFile`]'s iteration: anything that can hand back
+/// successive parts according to a [`LengthSpec`]. Implemented by
+/// [`ContinuableFile`] and [`ReadSlice`].
+pub trait NextSource {
+    fn next_part(&mut self, lns: LengthSpec) -> io::Result<FileHandle>;
+
+    /// `(known upper bound, current offset)`, in whatever coordinate frame
+    /// the source uses internally; used only by [`ChunkedFile::size_hint`].
+    fn bound_hint(&self) -> (Option<u64>, u64);
+}
+
 #[must_use]
-pub struct ChunkedFile {
-    pub cf: ContinuableFile,
+pub struct ChunkedFile<C = ContinuableFile<fs::File>> {
+    pub cf: C,
     pub lns: LengthSpec,
+    /// the current chunk (if any) plus how many of its leading bytes have
+    /// already been consumed through [`io::Read`]/[`io::BufRead`]; mirrors
+    /// the factored-out buffer of std's `BufReader`
+    buf: Option<(FileHandle, usize)>,
+}
+
+/// `into_chunks()` always reads forward from the current position, so
+/// default an unset (`None`) advice to [`Advice::Sequential`]; an explicit
+/// choice, including [`Advice::Normal`], is left untouched.
+#[inline]
+fn chunked_lns(lns: LengthSpec) -> LengthSpec {
+    LengthSpec {
+        advice: lns.advice.or(Some(Advice::Sequential)),
+        ..lns
+    }
 }
 
-impl ContinuableFile {
-    pub fn new(file: fs::File) -> Self {
+impl<F: Borrow<fs::File> + PosRead> ContinuableFile<F> {
+    pub fn new(file: F) -> Self {
         let mut ret = Self {
             file,
             flen: None,
@@ -122,28 +268,32 @@ impl ContinuableFile {
     }
 
     #[inline]
-    pub fn into_chunks(self, lns: LengthSpec) -> ChunkedFile {
-        ChunkedFile { cf: self, lns }
+    pub fn into_chunks(self, lns: LengthSpec) -> ChunkedFile<Self> {
+        ChunkedFile { cf: self, lns: chunked_lns(lns), buf: None }
     }
 
     #[inline]
     pub fn sync_len(&mut self) {
-        self.flen = get_file_len(&self.file);
+        self.flen = get_file_len(self.file.borrow());
     }
 
     /// Tries to read the next part of the file contents, according to the LengthSpec
     pub fn next(&mut self, lns: LengthSpec) -> io::Result<FileHandle> {
-        let rfh = read_part_from_file_intern(&mut self.file, self.offset, lns, self.flen)?;
+        let rfh = read_part_from_file_intern(&self.file, self.offset, lns, self.flen)?;
         self.offset += rfh.len() as u64;
         Ok(rfh)
     }
+}
 
-    fn get_soor_err() -> io::Error {
-        io::Error::new(io::ErrorKind::InvalidInput, "seek out of range")
+impl<F> ContinuableFile<F> {
+    /// Recovers the underlying file (or file reference/handle).
+    #[inline]
+    pub fn into_inner(self) -> F {
+        self.file
     }
 }
 
-impl io::Seek for ContinuableFile {
+impl<F> io::Seek for ContinuableFile<F> {
     fn seek(&mut self, pos: io::SeekFrom) -> io::Result<u64> {
         use io::SeekFrom::*;
 
@@ -157,12 +307,12 @@ impl io::Seek for ContinuableFile {
                 return Ok(y);
             }
         }
-        Err(Self::get_soor_err())
+        Err(seek_out_of_range_err())
     }
 
     //#[inline(always)]
     //fn stream_len(&mut self) -> io::Result<u64> {
-    //    self.flen.ok_or_else(Self::get_soor_err)
+    //    self.flen.ok_or_else(seek_out_of_range_err)
     //}
 
     #[inline(always)]
@@ -171,18 +321,30 @@ impl io::Seek for ContinuableFile {
     }
 }
 
-impl std::iter::Iterator for ChunkedFile {
+impl<F: Borrow<fs::File> + PosRead> NextSource for ContinuableFile<F> {
+    #[inline]
+    fn next_part(&mut self, lns: LengthSpec) -> io::Result<FileHandle> {
+        self.next(lns)
+    }
+
+    #[inline]
+    fn bound_hint(&self) -> (Option<u64>, u64) {
+        (self.flen, self.offset)
+    }
+}
+
+impl<C: NextSource> std::iter::Iterator for ChunkedFile<C> {
     type Item = io::Result<FileHandle>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        match self.cf.next(self.lns) {
+        match self.cf.next_part(self.lns) {
             Ok(ref x) if x.is_empty() => None,
             item => Some(item),
         }
     }
 
     fn size_hint(&self) -> (usize, Option<usize>) {
-        let (flen, offset) = (self.cf.flen, self.cf.offset);
+        let (flen, offset) = self.cf.bound_hint();
         let lower_bound =
             flen.and_then(|x| self.lns.bound.map(|y| ((x - offset) as usize) / y));
         (lower_bound.unwrap_or(0), lower_bound.map(|x| x + 1))
@@ -190,7 +352,7 @@ impl std::iter::Iterator for ChunkedFile {
 }
 
 #[delegate(self.cf)]
-impl io::Seek for ChunkedFile {
+impl<C: io::Seek> io::Seek for ChunkedFile<C> {
     fn seek(&mut self, pos: io::SeekFrom) -> io::Result<u64> {}
 
     //#[cfg(feature = "seek_stream_len")]
@@ -198,3 +360,356 @@ impl io::Seek for ChunkedFile {
 
     fn stream_position(&mut self) -> io::Result<u64> {}
 }
+
+/// A bounded, half-open `[start, end)` byte window onto an underlying file:
+/// reads and seeks are translated into absolute file offsets; reads are
+/// clamped to the window and seeks outside it fail with a seek-out-of-range
+/// error, so the rest of the file is never exposed. This is the key
+/// primitive for reading one member out of a concatenated/container format;
+/// use [`ReadSlice::into_chunks`] to iterate just that window.
+#[must_use]
+pub struct ReadSlice<F = fs::File> {
+    file: F,
+    start: u64,
+    end: u64,
+    offset: u64,
+}
+
+impl<F> ReadSlice<F> {
+    /// Restricts `file` to the half-open window `[start, end)`
+    /// (`end` is clamped up to `start` if given smaller).
+    pub fn new(file: F, start: u64, end: u64) -> Self {
+        Self {
+            file,
+            start,
+            end: end.max(start),
+            offset: start,
+        }
+    }
+
+    /// Recovers the underlying file (or file reference/handle).
+    #[inline]
+    pub fn into_inner(self) -> F {
+        self.file
+    }
+}
+
+impl<F: Borrow<fs::File> + PosRead> ReadSlice<F> {
+    #[inline]
+    pub fn into_chunks(self, lns: LengthSpec) -> ChunkedFile<Self> {
+        ChunkedFile { cf: self, lns: chunked_lns(lns), buf: None }
+    }
+
+    /// Tries to read the next part of the window's contents, according to
+    /// the LengthSpec; the requested bound is capped to the bytes still
+    /// remaining in `[start, end)`.
+    pub fn next(&mut self, lns: LengthSpec) -> io::Result<FileHandle> {
+        let remaining = (self.end - self.offset) as usize;
+        let bound = lns.bound.map_or(remaining, |b| b.min(remaining));
+        let rfh = read_part_from_file_intern(
+            &self.file,
+            self.offset,
+            LengthSpec {
+                bound: Some(bound),
+                ..lns
+            },
+            Some(self.end),
+        )?;
+        self.offset += rfh.len() as u64;
+        Ok(rfh)
+    }
+}
+
+impl<F> io::Seek for ReadSlice<F> {
+    /// All of `Start`/`Current`/`End` (and [`stream_position`](io::Seek::stream_position))
+    /// operate in the same, window-relative coordinate frame: position `0`
+    /// is `self.start`, matching a freshly-opened window.
+    fn seek(&mut self, pos: io::SeekFrom) -> io::Result<u64> {
+        use io::SeekFrom::*;
+
+        if let Some(y) = match pos {
+            Start(x) => self.start.checked_add(x),
+            End(x) => do_offset_add(self.end, x),
+            Current(x) => do_offset_add(self.offset, x),
+        } {
+            if y >= self.start && y <= self.end {
+                self.offset = y;
+                return Ok(y - self.start);
+            }
+        }
+        Err(seek_out_of_range_err())
+    }
+
+    #[inline(always)]
+    fn stream_position(&mut self) -> io::Result<u64> {
+        Ok(self.offset - self.start)
+    }
+}
+
+impl<F: Borrow<fs::File> + PosRead> NextSource for ReadSlice<F> {
+    #[inline]
+    fn next_part(&mut self, lns: LengthSpec) -> io::Result<FileHandle> {
+        self.next(lns)
+    }
+
+    #[inline]
+    fn bound_hint(&self) -> (Option<u64>, u64) {
+        (Some(self.end), self.offset)
+    }
+}
+
+impl<C: NextSource> io::BufRead for ChunkedFile<C> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        if self.buf.as_ref().is_none_or(|(fh, pos)| *pos >= fh.len()) {
+            let fh = self.cf.next_part(self.lns)?;
+            if fh.is_empty() && self.lns.is_exact {
+                return Err(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    "exact-length read hit end of file",
+                ));
+            }
+            self.buf = Some((fh, 0));
+        }
+        let (ref fh, pos) = *self.buf.as_ref().unwrap();
+        Ok(&fh[pos..])
+    }
+
+    fn consume(&mut self, amt: usize) {
+        if let Some((fh, pos)) = self.buf.as_mut() {
+            // mirrors std's `BufReader::consume`, which saturates at the end
+            // of the buffer instead of panicking on an over-long `amt`
+            *pos = (*pos + amt).min(fh.len());
+        }
+    }
+}
+
+impl<C: NextSource> io::Read for ChunkedFile<C> {
+    fn read(&mut self, out: &mut [u8]) -> io::Result<usize> {
+        let buf = self.fill_buf()?;
+        let n = buf.len().min(out.len());
+        out[..n].copy_from_slice(&buf[..n]);
+        self.consume(n);
+        Ok(n)
+    }
+}
+
+/// A shared handle to a [`FileHandle`], used as the cache value of
+/// [`CachedFile`]; implemented for [`std::sync::Arc`] and [`std::rc::Rc`].
+pub trait SharedHandle: Clone {
+    fn new(fh: FileHandle) -> Self;
+}
+
+impl SharedHandle for std::sync::Arc<FileHandle> {
+    #[inline]
+    fn new(fh: FileHandle) -> Self {
+        std::sync::Arc::new(fh)
+    }
+}
+
+impl SharedHandle for std::rc::Rc<FileHandle> {
+    #[inline]
+    fn new(fh: FileHandle) -> Self {
+        std::rc::Rc::new(fh)
+    }
+}
+
+/// A block-caching reader for random-access parsers: wraps a
+/// [`ContinuableFile`] and memoizes every region it has materialized for a
+/// bounded request, keyed on `(offset, len.bound, len.is_exact)` (unbounded
+/// requests are never cached, see [`CachedFile::read_at`]), so repeatedly dereferencing the same
+/// offset+size range (as object/archive parsers tend to do) reuses the
+/// same mapped/buffered [`FileHandle`] instead of remapping or re-reading it.
+///
+/// `P` selects the shared-pointer type handed back to callers: the default
+/// [`std::sync::Arc`] works across threads, [`std::rc::Rc`] is cheaper for
+/// single-threaded use. Entries are never evicted; call [`CachedFile::clear`]
+/// to drop them.
+#[must_use]
+pub struct CachedFile<F = fs::File, P = std::sync::Arc<FileHandle>> {
+    cf: ContinuableFile<F>,
+    cache: std::collections::HashMap<(u64, usize, bool), P>,
+}
+
+impl<F: Borrow<fs::File> + PosRead, P: SharedHandle> CachedFile<F, P> {
+    pub fn new(file: F) -> Self {
+        Self::from_continuable(ContinuableFile::new(file))
+    }
+
+    pub fn from_continuable(cf: ContinuableFile<F>) -> Self {
+        Self {
+            cf,
+            cache: std::collections::HashMap::new(),
+        }
+    }
+
+    /// Returns the (possibly cached) contents of `len.bound` bytes starting
+    /// at `offset`. Only requests with a concrete `len.bound` are cached,
+    /// since that bound is part of the cache key, alongside `len.is_exact`
+    /// (a non-exact short read must never be handed back for a later exact
+    /// request at the same offset/bound, since those differ in observable
+    /// contents/errors); an unbounded `len` is always read fresh and never
+    /// inserted into the cache.
+    pub fn read_at(&mut self, offset: u64, len: LengthSpec) -> io::Result<P> {
+        let key = len.bound.map(|bound| (offset, bound, len.is_exact));
+        if let Some(ref key) = key {
+            if let Some(cached) = self.cache.get(key) {
+                return Ok(cached.clone());
+            }
+        }
+        let fh = P::new(read_part_from_file_intern(
+            &self.cf.file,
+            offset,
+            len,
+            self.cf.flen,
+        )?);
+        if let Some(key) = key {
+            self.cache.insert(key, fh.clone());
+        }
+        Ok(fh)
+    }
+
+    /// Drops all cached buffers, without affecting the underlying file.
+    #[inline]
+    pub fn clear(&mut self) {
+        self.cache.clear();
+    }
+
+    /// Drops the cache and recovers the underlying file.
+    #[inline]
+    pub fn into_inner(self) -> F {
+        self.cf.into_inner()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Read, Seek};
+
+    #[test]
+    fn chunked_file_read_to_end_reassembles_the_file() {
+        let mut tf = tempfile::NamedTempFile::new().unwrap();
+        std::io::Write::write_all(&mut tf, b"0123456789").unwrap();
+        let file = tf.reopen().unwrap();
+
+        let mut cf = ContinuableFile::new(file).into_chunks(LengthSpec {
+            bound: Some(4),
+            is_exact: false,
+            ..LengthSpec::default()
+        });
+        let mut out = Vec::new();
+        cf.read_to_end(&mut out).unwrap();
+        assert_eq!(&out, b"0123456789");
+    }
+
+    #[test]
+    fn chunked_file_exact_read_errors_at_eof() {
+        let mut tf = tempfile::NamedTempFile::new().unwrap();
+        std::io::Write::write_all(&mut tf, b"0123456789").unwrap();
+        let file = tf.reopen().unwrap();
+
+        // 10 bytes isn't a multiple of the 4-byte chunk bound, so the last
+        // chunk is a short (but non-empty) read, which is_exact allows; the
+        // *next* read, finding nothing left, is what must fail.
+        let mut cf = ContinuableFile::new(file).into_chunks(LengthSpec {
+            bound: Some(4),
+            is_exact: true,
+            ..LengthSpec::default()
+        });
+        let mut out = Vec::new();
+        let err = cf.read_to_end(&mut out).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+        assert_eq!(&out, b"0123456789");
+    }
+
+    #[test]
+    fn read_part_for_write_round_trips_through_flush() {
+        let mut tf = tempfile::NamedTempFile::new().unwrap();
+        std::io::Write::write_all(&mut tf, &[0u8; 10]).unwrap();
+        let mut file = tf.reopen().unwrap();
+
+        let mut fh = read_part_for_write(
+            &mut file,
+            0,
+            LengthSpec {
+                bound: Some(10),
+                is_exact: true,
+                ..LengthSpec::default()
+            },
+            false,
+        )
+        .unwrap();
+        fh.as_mut_slice().unwrap().copy_from_slice(b"0123456789");
+        fh.flush().unwrap();
+        drop(fh);
+
+        let contents = std::fs::read(tf.path()).unwrap();
+        assert_eq!(&contents, b"0123456789");
+    }
+
+    #[test]
+    fn read_slice_seek_is_window_relative() {
+        // `F = ()` is fine here: `Seek` carries no bound on `F`.
+        let mut rs = ReadSlice::new((), 50, 80);
+        assert_eq!(rs.stream_position().unwrap(), 0);
+
+        assert_eq!(rs.seek(io::SeekFrom::Start(10)).unwrap(), 10);
+        assert_eq!(rs.stream_position().unwrap(), 10);
+
+        rs.rewind().unwrap();
+        assert_eq!(rs.stream_position().unwrap(), 0);
+
+        assert_eq!(rs.seek(io::SeekFrom::End(0)).unwrap(), 30);
+        assert_eq!(rs.seek(io::SeekFrom::Current(-5)).unwrap(), 25);
+
+        // out-of-window seeks are rejected
+        assert!(rs.seek(io::SeekFrom::Start(31)).is_err());
+        assert!(rs.seek(io::SeekFrom::Current(-100)).is_err());
+    }
+
+    #[test]
+    fn cached_file_keys_on_is_exact() {
+        let mut tf = tempfile::NamedTempFile::new().unwrap();
+        std::io::Write::write_all(&mut tf, b"0123456789").unwrap();
+        let file = tf.reopen().unwrap();
+
+        let mut cf: CachedFile = CachedFile::new(file);
+        let spec = |is_exact| LengthSpec {
+            bound: Some(4),
+            is_exact,
+            ..LengthSpec::default()
+        };
+
+        // a non-exact read at (offset, bound) must not seed the cache entry
+        // that a later exact request at the same (offset, bound) reads from
+        let non_exact = cf.read_at(0, spec(false)).unwrap();
+        let exact = cf.read_at(0, spec(true)).unwrap();
+        assert!(!std::sync::Arc::ptr_eq(&non_exact, &exact));
+
+        // but two requests with matching keys (including `is_exact`) do share
+        // a single cache entry
+        let exact_again = cf.read_at(0, spec(true)).unwrap();
+        assert!(std::sync::Arc::ptr_eq(&exact, &exact_again));
+    }
+
+    #[test]
+    fn cached_file_read_at_past_eof_does_not_panic() {
+        let mut tf = tempfile::NamedTempFile::new().unwrap();
+        std::io::Write::write_all(&mut tf, b"0123456789").unwrap();
+        let file = tf.reopen().unwrap();
+
+        // an offset/size pair dereferenced from untrusted archive contents
+        // may point past the end of the file; this must not panic
+        let mut cf: CachedFile = CachedFile::new(file);
+        let fh = cf
+            .read_at(
+                1000,
+                LengthSpec {
+                    bound: Some(4),
+                    ..LengthSpec::default()
+                },
+            )
+            .unwrap();
+        assert!(fh.is_empty());
+    }
+}